@@ -30,6 +30,8 @@
 //! let response = response.body("hello me".as_bytes().to_vec()).unwrap();
 //! http_request.respond(response).unwrap();
 //! ```
+extern crate brotli;
+extern crate flate2;
 extern crate http;
 extern crate httparse;
 
@@ -38,17 +40,55 @@ mod tests;
 
 use std::error::Error;
 use std::fmt::{Display, Formatter};
-use std::io::{BufRead, BufReader, Write};
+use std::io::{BufRead, BufReader, Read, Write};
 use std::net::TcpStream;
 
+use flate2::read::{DeflateDecoder, GzDecoder};
+
 pub use http::Response;
 
+/// Number of headers allocated for the first parsing attempt.
+const DEFAULT_HEADER_CAPACITY: usize = 16;
+
+/// Default upper bound on the number of headers that will be allocated for a single message
+/// before `httparse::Error::TooManyHeaders` is surfaced as a real error, used by the standalone
+/// `parse_into_request`/`parse_into_response` functions and as the default for
+/// [`HttpRequestOptions::max_header_capacity`].
+const DEFAULT_MAX_HEADER_CAPACITY: usize = 256;
+
+/// Options controlling [`HttpRequest::from_tcp_stream_with_options`].
+#[derive(Clone, Copy)]
+pub struct HttpRequestOptions {
+	/// When the incoming request carries `expect: 100-continue`, automatically write an
+	/// interim `100 Continue` response before reading the body. When `false`, a
+	/// `417 Expectation Failed` is written instead and the request is rejected.
+	///
+	/// Defaults to `true`.
+	pub auto_continue: bool,
+
+	/// Upper bound on the number of headers that will be allocated for a single message
+	/// before `httparse::Error::TooManyHeaders` is surfaced as a real error.
+	///
+	/// Defaults to `256`.
+	pub max_header_capacity: usize,
+}
+
+impl Default for HttpRequestOptions {
+	fn default() -> Self {
+		HttpRequestOptions {
+			auto_continue: true,
+			max_header_capacity: DEFAULT_MAX_HEADER_CAPACITY,
+		}
+	}
+}
+
 /// Represents a HTTP request.
 ///
 /// The http structure which contains the parsed `http::Request`.
 /// Can be used to respond with a `http::Response`.
 pub struct HttpRequest {
-	tcp_stream: TcpStream,
+	tcp_stream: BufReader<TcpStream>,
+	options: HttpRequestOptions,
 	pub request: http::Request<Vec<u8>>,
 }
 
@@ -62,15 +102,58 @@ impl HttpRequest {
 	/// let http_request = kl_http::HttpRequest::from_tcp_stream(stream);
 	/// ```
 	pub fn from_tcp_stream(stream: TcpStream) -> Result<Self, HttpRequestError> {
-		let request = {
-			let mut reader = BufReader::new(&stream);
-			parse_into_request(&mut reader)
+		HttpRequest::from_tcp_stream_with_options(stream, HttpRequestOptions::default())
+	}
+
+	/// Creates a new `HttpRequest` from the incoming stream, with control over how the
+	/// `expect: 100-continue` handshake is handled.
+	///
+	/// # Implementation notes
+	/// When the request carries an `expect: 100-continue` header and `options.auto_continue`
+	/// is `true` (the default), an interim `HTTP/1.1 100 Continue\r\n\r\n` is written back to
+	/// the `TcpStream` before the body is read, as the client is withholding the body until
+	/// it sees that response. If `options.auto_continue` is `false`, a `417 Expectation
+	/// Failed` is written instead and parsing stops short of reading the body, letting a
+	/// server reject oversized or unwanted requests without blocking on bytes the client
+	/// never sends.
+	///
+	/// # Example
+	/// ```ignore
+	/// let mut stream = ::std::net::TcpStream::connect("127.0.0.1:8080").unwrap();
+	///
+	/// let options = kl_http::HttpRequestOptions { auto_continue: false, ..Default::default() };
+	/// let http_request = kl_http::HttpRequest::from_tcp_stream_with_options(stream, options);
+	/// ```
+	pub fn from_tcp_stream_with_options(
+		stream: TcpStream,
+		options: HttpRequestOptions,
+	) -> Result<Self, HttpRequestError> {
+		let mut tcp_stream = BufReader::new(stream);
+		let head_bytes = read_head(&mut tcp_stream)?;
+
+		let mut capacity = DEFAULT_HEADER_CAPACITY;
+		let mut headers_storage = vec![httparse::EMPTY_HEADER; capacity];
+		let http_parse_request = loop {
+			let mut http_parse_request = httparse::Request::new(&mut headers_storage);
+			match http_parse_request.parse(&head_bytes) {
+				Ok(_) => break http_parse_request,
+				Err(httparse::Error::TooManyHeaders)
+					if capacity < options.max_header_capacity =>
+				{
+					capacity = (capacity * 2).min(options.max_header_capacity);
+					headers_storage = vec![httparse::EMPTY_HEADER; capacity];
+				}
+				Err(err) => return Err(err.into()),
+			}
 		};
 
-		let request = request?;
+		handle_expect_continue(&mut tcp_stream, http_parse_request.headers, options)?;
+
+		let request = build_request_from_parsed_head(http_parse_request, &mut tcp_stream)?;
 
 		Ok(HttpRequest {
-			tcp_stream: stream,
+			tcp_stream: tcp_stream,
+			options: options,
 			request: request,
 		})
 	}
@@ -108,10 +191,129 @@ impl HttpRequest {
 		}
 		let response_bytes: Vec<u8> = response.to_http();
 
-		self.tcp_stream.write(&response_bytes)?;
+		self.tcp_stream.get_mut().write_all(&response_bytes)?;
 
 		Ok(())
 	}
+
+	/// Returns whether the underlying connection should be kept alive for a further request,
+	/// following the HTTP/1.1 rules: a connection stays open on `HTTP/1.1` unless the
+	/// `connection` header contains `close`, and on `HTTP/1.0` only if the `connection`
+	/// header contains `keep-alive`. A `connection` header containing `upgrade` always
+	/// keeps the connection open.
+	pub fn keep_alive(&self) -> bool {
+		let connection = self
+			.request
+			.headers()
+			.get("connection")
+			.and_then(|v| v.to_str().ok())
+			.unwrap_or("")
+			.to_ascii_lowercase();
+
+		if connection.contains("upgrade") {
+			return true;
+		}
+
+		match self.request.version() {
+			http::Version::HTTP_10 => connection.contains("keep-alive"),
+			_ => !connection.contains("close"),
+		}
+	}
+
+	/// Parses the next request from the same `TcpStream`, re-using the buffered bytes left
+	/// over from the previous request.
+	///
+	/// Returns `Ok(None)` when [`keep_alive`](HttpRequest::keep_alive) reports the connection
+	/// should not be reused, or the peer has closed its half of the connection.
+	///
+	/// # Example
+	/// ```ignore
+	/// let mut http_request = kl_http::HttpRequest::from_tcp_stream(stream).unwrap();
+	/// http_request.respond(response).unwrap();
+	///
+	/// while let Some(mut next) = http_request.next_request().unwrap() {
+	///     next.respond(response).unwrap();
+	///     http_request = next;
+	/// }
+	/// ```
+	pub fn next_request(mut self) -> Result<Option<HttpRequest>, HttpRequestError> {
+		if !self.keep_alive() {
+			return Ok(None);
+		}
+
+		if self.tcp_stream.fill_buf()?.is_empty() {
+			return Ok(None);
+		}
+
+		let head_bytes = read_head(&mut self.tcp_stream)?;
+
+		let mut capacity = DEFAULT_HEADER_CAPACITY;
+		let mut headers_storage = vec![httparse::EMPTY_HEADER; capacity];
+		let http_parse_request = loop {
+			let mut http_parse_request = httparse::Request::new(&mut headers_storage);
+			match http_parse_request.parse(&head_bytes) {
+				Ok(_) => break http_parse_request,
+				Err(httparse::Error::TooManyHeaders)
+					if capacity < self.options.max_header_capacity =>
+				{
+					capacity = (capacity * 2).min(self.options.max_header_capacity);
+					headers_storage = vec![httparse::EMPTY_HEADER; capacity];
+				}
+				Err(err) => return Err(err.into()),
+			}
+		};
+
+		handle_expect_continue(&mut self.tcp_stream, http_parse_request.headers, self.options)?;
+
+		let request = build_request_from_parsed_head(http_parse_request, &mut self.tcp_stream)?;
+
+		Ok(Some(HttpRequest {
+			tcp_stream: self.tcp_stream,
+			options: self.options,
+			request: request,
+		}))
+	}
+}
+
+/// A simple HTTP client, the counterpart to `HttpRequest` for the other side of the exchange.
+pub struct HttpClient;
+
+impl HttpClient {
+	/// Sends a `http::Request` over the given `TcpStream` and reads back the `http::Response`.
+	///
+	/// # Implementation notes
+	/// If the `http::Request` does not contain a header `"content-length"`, a header will be added using the Body(`Vec<u8>`) length, mirroring `HttpRequest::respond`.
+	///
+	/// # Example
+	/// ```ignore
+	/// let stream = ::std::net::TcpStream::connect("127.0.0.1:8080").unwrap();
+	///
+	/// let mut request = http::Request::builder();
+	/// request.method(http::Method::GET);
+	/// let request = request.body(Vec::new()).unwrap();
+	///
+	/// let response = kl_http::HttpClient::send(stream, request).unwrap();
+	/// ```
+	pub fn send(
+		mut stream: TcpStream,
+		mut request: http::Request<Vec<u8>>,
+	) -> Result<http::Response<Vec<u8>>, HttpRequestError> {
+		if !request.headers().iter().any(|x| x.0 == "content-length") {
+			// i want to add in a content length if there is a body
+			let body_len = request.body().len();
+			request.headers_mut().insert(
+				"content-length",
+				http::header::HeaderValue::from_bytes(body_len.to_string().as_bytes())
+					.expect("Failed reading a usize into string? This shouldn't happen."),
+			);
+		}
+		let request_bytes: Vec<u8> = request.to_http();
+
+		stream.write_all(&request_bytes)?;
+
+		let mut reader = BufReader::new(stream);
+		parse_into_response(&mut reader)
+	}
 }
 
 /// Takes a readable item and returns a `http::Request`.
@@ -136,9 +338,33 @@ where
 {
 	let request_bytes = read_head(&mut reader)?;
 
-	let mut headers = [httparse::EMPTY_HEADER; 16];
-	let mut http_parse_request = httparse::Request::new(&mut headers);
-	http_parse_request.parse(&request_bytes)?;
+	let mut capacity = DEFAULT_HEADER_CAPACITY;
+	let mut headers_storage = vec![httparse::EMPTY_HEADER; capacity];
+	let http_parse_request = loop {
+		let mut http_parse_request = httparse::Request::new(&mut headers_storage);
+		match http_parse_request.parse(&request_bytes) {
+			Ok(_) => break http_parse_request,
+			Err(httparse::Error::TooManyHeaders) if capacity < DEFAULT_MAX_HEADER_CAPACITY => {
+				capacity = (capacity * 2).min(DEFAULT_MAX_HEADER_CAPACITY);
+				headers_storage = vec![httparse::EMPTY_HEADER; capacity];
+			}
+			Err(err) => return Err(err.into()),
+		}
+	};
+
+	build_request_from_parsed_head(http_parse_request, &mut reader)
+}
+
+/// Builds a `http::Request` from an already-parsed `httparse::Request` head, reading the
+/// body (chunked or content-length delimited) from `reader`.
+fn build_request_from_parsed_head<R>(
+	http_parse_request: httparse::Request,
+	reader: &mut R,
+) -> Result<http::Request<Vec<u8>>, HttpRequestError>
+where
+	R: BufRead,
+{
+	let is_chunked = is_chunked_transfer_encoding(http_parse_request.headers);
 	let body_length: usize = match http_parse_request
 		.headers
 		.iter()
@@ -155,13 +381,29 @@ where
 	if let Some(path) = http_parse_request.path {
 		request.uri(path);
 	}
-	request.version(http::Version::HTTP_11);
+	request.version(match http_parse_request.version {
+		Some(0) => http::Version::HTTP_10,
+		_ => http::Version::HTTP_11,
+	});
 
 	for kvp in http_parse_request.headers {
+		if is_chunked
+			&& (kvp.name.eq_ignore_ascii_case("transfer-encoding")
+				|| kvp.name.eq_ignore_ascii_case("content-length"))
+		{
+			// The body is de-chunked below; drop the now-stale
+			// transfer-encoding and any client-supplied content-length so the
+			// synthesized one isn't appended alongside it.
+			continue;
+		}
 		request.header(kvp.name, kvp.value);
 	}
 
-	let body: Vec<u8> = {
+	let body: Vec<u8> = if is_chunked {
+		let body = read_chunked_body(reader)?;
+		request.header("content-length", body.len().to_string());
+		body
+	} else {
 		let mut body = vec![0u8; body_length];
 		reader.read_exact(&mut body)?;
 
@@ -173,6 +415,90 @@ where
 	Ok(request)
 }
 
+/// Returns `true` when `headers` contains an `expect` header equal to `100-continue`
+/// (case-insensitive).
+fn expects_100_continue(headers: &[httparse::Header]) -> bool {
+	headers
+		.iter()
+		.find(|header| header.name.eq_ignore_ascii_case("expect"))
+		.map(|header| {
+			String::from_utf8_lossy(header.value)
+				.trim()
+				.eq_ignore_ascii_case("100-continue")
+		})
+		.unwrap_or(false)
+}
+
+/// Honors an `expect: 100-continue` header found on a parsed request head, writing the
+/// interim `100 Continue` (or rejecting with `417 Expectation Failed`) to `tcp_stream`
+/// before its body is read. Used by both `from_tcp_stream_with_options` and
+/// `HttpRequest::next_request`, so the handshake is honored on every request on a
+/// kept-alive connection, not just the first.
+fn handle_expect_continue(
+	tcp_stream: &mut BufReader<TcpStream>,
+	headers: &[httparse::Header],
+	options: HttpRequestOptions,
+) -> Result<(), HttpRequestError> {
+	if expects_100_continue(headers) {
+		if options.auto_continue {
+			tcp_stream
+				.get_mut()
+				.write_all(b"HTTP/1.1 100 Continue\r\n\r\n")?;
+		} else {
+			tcp_stream
+				.get_mut()
+				.write_all(b"HTTP/1.1 417 Expectation Failed\r\n\r\n")?;
+			return Err(HttpRequestError::ParsingError(
+				"Client sent 'expect: 100-continue' but auto_continue was disabled.".to_string(),
+			));
+		}
+	}
+
+	Ok(())
+}
+
+#[test]
+fn test_parse_into_request_chunked() {
+	let incoming_request = b"GET / HTTP/1.1\r\ntransfer-encoding: chunked\r\n\r\n4\r\nWiki\r\n5\r\npedia\r\n0\r\n\r\n";
+	let mut incoming_request = &incoming_request[..];
+	let request = parse_into_request(&mut incoming_request).unwrap();
+
+	assert_eq!(request.body(), &b"Wikipedia".to_vec());
+	assert!(request.headers().get("transfer-encoding").is_none());
+	assert_eq!(request.headers().get_all("content-length").iter().count(), 1);
+}
+
+#[test]
+fn test_parse_into_request_chunked_ignores_smuggled_content_length() {
+	let incoming_request = b"GET / HTTP/1.1\r\ntransfer-encoding: chunked\r\ncontent-length: 4\r\n\r\n4\r\nWiki\r\n5\r\npedia\r\n0\r\n\r\n";
+	let mut incoming_request = &incoming_request[..];
+	let request = parse_into_request(&mut incoming_request).unwrap();
+
+	assert_eq!(request.body(), &b"Wikipedia".to_vec());
+	assert_eq!(
+		request
+			.headers()
+			.get_all("content-length")
+			.iter()
+			.collect::<Vec<_>>(),
+		vec!["9"]
+	);
+}
+
+#[test]
+fn test_parse_into_request_grows_header_capacity() {
+	let mut incoming_request = b"GET / HTTP/1.1\r\n".to_vec();
+	for i in 0..(DEFAULT_HEADER_CAPACITY + 4) {
+		incoming_request.extend_from_slice(format!("x-header-{}: {}\r\n", i, i).as_bytes());
+	}
+	incoming_request.extend_from_slice(b"\r\n");
+
+	let mut incoming_request = &incoming_request[..];
+	let request = parse_into_request(&mut incoming_request).unwrap();
+
+	assert_eq!(request.headers().len(), DEFAULT_HEADER_CAPACITY + 4);
+}
+
 /// Takes a readable item and returns a `http::Response`.
 ///
 /// Reading `TcpStream` is inefficient ([see here](https://doc.rust-lang.org/stable/std/io/struct.BufReader.html)),
@@ -200,9 +526,22 @@ where
 	R: BufRead,
 {
 	let response_bytes = read_head(&mut reader)?;
-	let mut headers = [httparse::EMPTY_HEADER; 16];
-	let mut http_parse_response = httparse::Response::new(&mut headers);
-	http_parse_response.parse(&response_bytes)?;
+
+	let mut capacity = DEFAULT_HEADER_CAPACITY;
+	let mut headers_storage = vec![httparse::EMPTY_HEADER; capacity];
+	let http_parse_response = loop {
+		let mut http_parse_response = httparse::Response::new(&mut headers_storage);
+		match http_parse_response.parse(&response_bytes) {
+			Ok(_) => break http_parse_response,
+			Err(httparse::Error::TooManyHeaders) if capacity < DEFAULT_MAX_HEADER_CAPACITY => {
+				capacity = (capacity * 2).min(DEFAULT_MAX_HEADER_CAPACITY);
+				headers_storage = vec![httparse::EMPTY_HEADER; capacity];
+			}
+			Err(err) => return Err(err.into()),
+		}
+	};
+
+	let is_chunked = is_chunked_transfer_encoding(http_parse_response.headers);
 	let body_length: usize = match http_parse_response
 		.headers
 		.iter()
@@ -216,10 +555,23 @@ where
 	response.version(http::Version::HTTP_11);
 
 	for kvp in http_parse_response.headers {
+		if is_chunked
+			&& (kvp.name.eq_ignore_ascii_case("transfer-encoding")
+				|| kvp.name.eq_ignore_ascii_case("content-length"))
+		{
+			// The body is de-chunked below; drop the now-stale
+			// transfer-encoding and any server-supplied content-length so the
+			// synthesized one isn't appended alongside it.
+			continue;
+		}
 		response.header(kvp.name, kvp.value);
 	}
 
-	let body: Vec<u8> = {
+	let body: Vec<u8> = if is_chunked {
+		let body = read_chunked_body(&mut reader)?;
+		response.header("content-length", body.len().to_string());
+		body
+	} else {
 		let mut body = vec![0u8; body_length];
 		reader.read_exact(&mut body)?;
 
@@ -231,6 +583,192 @@ where
 	Ok(response)
 }
 
+/// Takes a readable item and returns a `http::Request`, transparently decoding the body when
+/// a `content-encoding` header declares `gzip`, `deflate`, or `br`.
+///
+/// On decoding, the now-inaccurate `content-encoding` and `content-length` headers are
+/// stripped from the returned request. Callers that want the raw, still-encoded bytes should
+/// use [`parse_into_request`] instead.
+pub fn parse_into_request_decoded<R>(
+	reader: &mut R,
+) -> Result<http::Request<Vec<u8>>, HttpRequestError>
+where
+	R: BufRead,
+{
+	let mut request = parse_into_request(reader)?;
+	let mut headers = request.headers().clone();
+	decode_content_encoding(&mut headers, request.body_mut())?;
+	*request.headers_mut() = headers;
+
+	Ok(request)
+}
+
+/// Takes a readable item and returns a `http::Response`, transparently decoding the body when
+/// a `content-encoding` header declares `gzip`, `deflate`, or `br`.
+///
+/// On decoding, the now-inaccurate `content-encoding` and `content-length` headers are
+/// stripped from the returned response. Callers that want the raw, still-encoded bytes should
+/// use [`parse_into_response`] instead.
+pub fn parse_into_response_decoded<R>(
+	reader: &mut R,
+) -> Result<http::Response<Vec<u8>>, HttpRequestError>
+where
+	R: BufRead,
+{
+	let mut response = parse_into_response(reader)?;
+	let mut headers = response.headers().clone();
+	decode_content_encoding(&mut headers, response.body_mut())?;
+	*response.headers_mut() = headers;
+
+	Ok(response)
+}
+
+#[test]
+fn test_parse_into_response_decoded_gzip() {
+	use flate2::write::GzEncoder;
+	use flate2::Compression;
+	use std::io::Write as _;
+
+	let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+	encoder.write_all(b"Hello, world").unwrap();
+	let compressed = encoder.finish().unwrap();
+
+	let mut incoming_response = format!(
+		"HTTP/1.1 200 OK\r\ncontent-encoding: gzip\r\ncontent-length: {}\r\n\r\n",
+		compressed.len()
+	)
+	.into_bytes();
+	incoming_response.extend_from_slice(&compressed);
+
+	let mut incoming_response = &incoming_response[..];
+	let response = parse_into_response_decoded(&mut incoming_response).unwrap();
+
+	assert_eq!(response.body(), &b"Hello, world".to_vec());
+	assert!(response.headers().get("content-encoding").is_none());
+}
+
+/// Decodes `body` in place according to the `content-encoding` header, removing the
+/// `content-encoding`/`content-length` headers since they no longer describe the result.
+/// Leaves `body` untouched when there is no recognised `content-encoding`.
+fn decode_content_encoding(
+	headers: &mut http::HeaderMap,
+	body: &mut Vec<u8>,
+) -> Result<(), HttpRequestError> {
+	let encoding = headers
+		.get("content-encoding")
+		.and_then(|value| value.to_str().ok())
+		.map(|value| value.trim().to_ascii_lowercase());
+
+	let decoded = match encoding.as_ref().map(String::as_str) {
+		Some("gzip") => Some(decode_with(GzDecoder::new(&body[..]))?),
+		Some("deflate") => Some(decode_with(DeflateDecoder::new(&body[..]))?),
+		Some("br") => {
+			let mut out = Vec::new();
+			brotli::BrotliDecompress(&mut &body[..], &mut out)
+				.map_err(|err| HttpRequestError::ParsingError(format!("{}", err)))?;
+			Some(out)
+		}
+		_ => None,
+	};
+
+	if let Some(decoded) = decoded {
+		*body = decoded;
+		headers.remove("content-encoding");
+		headers.remove("content-length");
+	}
+
+	Ok(())
+}
+
+fn decode_with<R>(mut decoder: R) -> Result<Vec<u8>, HttpRequestError>
+where
+	R: Read,
+{
+	let mut out = Vec::new();
+	decoder
+		.read_to_end(&mut out)
+		.map_err(|err| HttpRequestError::ParsingError(format!("{}", err)))?;
+
+	Ok(out)
+}
+
+/// Returns `true` when `headers` contains a `transfer-encoding` header whose value
+/// contains `chunked` (case-insensitive), which takes precedence over `content-length`.
+fn is_chunked_transfer_encoding(headers: &[httparse::Header]) -> bool {
+	headers
+		.iter()
+		.find(|header| header.name.eq_ignore_ascii_case("transfer-encoding"))
+		.map(|header| {
+			String::from_utf8_lossy(header.value)
+				.to_ascii_lowercase()
+				.contains("chunked")
+		})
+		.unwrap_or(false)
+}
+
+/// Reads a `Transfer-Encoding: chunked` body from `reader`.
+///
+/// Each chunk is prefixed by a CRLF-terminated line holding the chunk size as hexadecimal,
+/// optionally followed by `;`-delimited chunk extensions which are ignored. A chunk size of
+/// `0` marks the end of the data chunks; any trailer header lines that follow are consumed
+/// up to the terminating empty CRLF line.
+fn read_chunked_body<R>(reader: &mut R) -> Result<Vec<u8>, HttpRequestError>
+where
+	R: BufRead,
+{
+	let mut body = Vec::new();
+
+	loop {
+		let mut size_line = Vec::new();
+		reader.read_until(b'\n', &mut size_line)?;
+
+		let size_line = String::from_utf8_lossy(&size_line);
+		let size_str = size_line.trim_end_matches(|c| c == '\r' || c == '\n');
+		let size_str = size_str.split(';').next().unwrap_or("").trim();
+		let chunk_size = usize::from_str_radix(size_str, 16).map_err(|_| {
+			HttpRequestError::ParsingError(format!("Invalid chunk size: '{}'", size_str))
+		})?;
+
+		if chunk_size == 0 {
+			loop {
+				let mut trailer_line = Vec::new();
+				let read_bytes = reader.read_until(b'\n', &mut trailer_line)?;
+				if read_bytes == 0 || trailer_line == b"\r\n" {
+					break;
+				}
+			}
+
+			break;
+		}
+
+		let mut chunk = vec![0u8; chunk_size];
+		read_exact_or_truncated(reader, &mut chunk, "chunk data")?;
+		body.extend_from_slice(&chunk);
+
+		let mut trailing_crlf = [0u8; 2];
+		read_exact_or_truncated(reader, &mut trailing_crlf, "chunk trailing CRLF")?;
+	}
+
+	Ok(body)
+}
+
+/// Like `Read::read_exact`, but a stream that ends mid-read is reported as a
+/// `HttpRequestError::ParsingError` (the stream was truncated mid-chunk) rather than the
+/// generic `HttpRequestError::IOError` the blanket `From<std::io::Error>` impl would produce.
+fn read_exact_or_truncated<R: Read>(
+	reader: &mut R,
+	buf: &mut [u8],
+	what: &str,
+) -> Result<(), HttpRequestError> {
+	reader.read_exact(buf).map_err(|err| {
+		if err.kind() == std::io::ErrorKind::UnexpectedEof {
+			HttpRequestError::ParsingError(format!("Stream ended while reading {}", what))
+		} else {
+			err.into()
+		}
+	})
+}
+
 fn read_head<R>(reader: &mut R) -> Result<Vec<u8>, HttpRequestError>
 where
 	R: BufRead,
@@ -1,55 +1,181 @@
 use super::*;
 extern crate http;
 
-#[cfg(test)]
 #[test]
-fn main() {
+fn test_http_request_roundtrip() {
+	use http::{Response, StatusCode};
 	use std::io::{BufReader, Write};
-	use MyHttp;
-	use HttpSerialise;
-	use http::{Request, Response, StatusCode};
 
 	let incoming_request = b"GET / HTTP/1.1\r\nuser-agent: Dart/2.0 (dart:io)\r\ncontent-type: text/plain; charset=utf-8\r\naccept-encoding: gzip\r\ncontent-length: 11\r\nhost: 10.0.2.2:8080\r\n\r\nHello world";
 
+	let listener =
+		::std::net::TcpListener::bind("127.0.0.1:0").expect("Failed listening connection.");
+	let addr = listener.local_addr().unwrap();
+
 	let listening_thread = ::std::thread::spawn(move || {
-		let listener =
-			::std::net::TcpListener::bind("127.0.0.1:8080").expect("Failed listening connection.");
+		let stream = listener
+			.accept()
+			.expect("Failed to accept connection.")
+			.0;
+		let mut http_request =
+			HttpRequest::from_tcp_stream(stream).expect("Failed to parse request.");
+
+		assert_eq!(http_request.request.to_http(), incoming_request.to_vec());
+
+		let mut response = Response::builder();
+		response.status(StatusCode::OK);
+		let response = response
+			.body("hello me".as_bytes().to_vec())
+			.expect("Couldn't add body");
+
+		http_request.respond(response).expect("Failed to respond.");
+	});
+
+	let mut s = ::std::net::TcpStream::connect(addr).unwrap();
+	s.write_all(incoming_request).unwrap();
+
+	let response = {
+		let mut reader = BufReader::new(&mut s);
+		parse_into_response(&mut reader).expect("Failed to parse response.")
+	};
 
-		for stream in listener.incoming() {
-			let mut myhttp = MyHttp::from_tcp_stream(stream.expect("Failed to return stream."));
+	assert_eq!(response.body(), &"hello me".as_bytes().to_vec());
 
-			assert_eq!(
-				myhttp.request.to_http(),
-				incoming_request.iter().map(|x| *x).collect::<Vec<u8>>()
-			);
+	listening_thread.join().expect("Listening thread panicked.");
+}
+
+#[test]
+fn test_keep_alive_serves_multiple_requests() {
+	use http::{Response, StatusCode};
+	use std::io::{BufReader, Write};
 
+	let listener = ::std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+	let addr = listener.local_addr().unwrap();
+
+	let listening_thread = ::std::thread::spawn(move || {
+		let stream = listener.accept().unwrap().0;
+		let mut http_request = HttpRequest::from_tcp_stream(stream).unwrap();
+
+		let mut served = 0;
+		loop {
 			let mut response = Response::builder();
 			response.status(StatusCode::OK);
 			let response = response
-				.body("hello me".as_bytes().to_vec())
-				.expect("Couldn't add body");
+				.body(format!("response {}", served).into_bytes())
+				.unwrap();
+			http_request.respond(response).unwrap();
+			served += 1;
 
-			myhttp.respond(response);
+			match http_request.next_request().unwrap() {
+				Some(next) => http_request = next,
+				None => break,
+			}
 		}
+
+		served
 	});
 
-	let mut s = ::std::net::TcpStream::connect("127.0.0.1:8080").unwrap();
+	let mut s = ::std::net::TcpStream::connect(addr).unwrap();
+	s.write_all(b"GET /one HTTP/1.1\r\nhost: localhost\r\n\r\n")
+		.unwrap();
+	s.write_all(b"GET /two HTTP/1.1\r\nhost: localhost\r\nconnection: close\r\n\r\n")
+		.unwrap();
 
-	s.write(incoming_request).unwrap();
+	let mut reader = BufReader::new(&mut s);
+	let first = parse_into_response(&mut reader).unwrap();
+	assert_eq!(first.body(), &b"response 0".to_vec());
 
-	let response = {
-		let mut reader = BufReader::new(&mut s);
-		::parse_into_response(&mut reader)
-	};
+	let second = parse_into_response(&mut reader).unwrap();
+	assert_eq!(second.body(), &b"response 1".to_vec());
+
+	let served = listening_thread.join().expect("Listening thread panicked.");
+	assert_eq!(served, 2);
+}
+
+#[test]
+fn test_expect_100_continue_auto_replies() {
+	use std::io::{Read, Write};
+
+	let listener = ::std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+	let addr = listener.local_addr().unwrap();
+
+	let listening_thread = ::std::thread::spawn(move || {
+		let stream = listener.accept().unwrap().0;
+		HttpRequest::from_tcp_stream(stream).expect("Failed to parse request.")
+	});
+
+	let mut s = ::std::net::TcpStream::connect(addr).unwrap();
+	s.write_all(b"POST / HTTP/1.1\r\nhost: localhost\r\ncontent-length: 5\r\nexpect: 100-continue\r\n\r\n")
+		.unwrap();
+
+	let mut interim = [0u8; 25];
+	s.read_exact(&mut interim).unwrap();
+	assert_eq!(&interim[..], &b"HTTP/1.1 100 Continue\r\n\r\n"[..]);
+
+	s.write_all(b"hello").unwrap();
+
+	let http_request = listening_thread.join().expect("Listening thread panicked.");
+	assert_eq!(http_request.request.body(), &b"hello".to_vec());
+}
+
+#[test]
+fn test_expect_100_continue_disabled_rejects_with_417() {
+	use std::io::{Read, Write};
+
+	let listener = ::std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+	let addr = listener.local_addr().unwrap();
+
+	let listening_thread = ::std::thread::spawn(move || {
+		let stream = listener.accept().unwrap().0;
+		HttpRequest::from_tcp_stream_with_options(
+			stream,
+			HttpRequestOptions {
+				auto_continue: false,
+				..Default::default()
+			},
+		)
+	});
+
+	let mut s = ::std::net::TcpStream::connect(addr).unwrap();
+	s.write_all(b"POST / HTTP/1.1\r\nhost: localhost\r\ncontent-length: 5\r\nexpect: 100-continue\r\n\r\n")
+		.unwrap();
+
+	let mut interim = [0u8; 35];
+	s.read_exact(&mut interim).unwrap();
+	assert_eq!(&interim[..], &b"HTTP/1.1 417 Expectation Failed\r\n\r\n"[..]);
+
+	let result = listening_thread.join().expect("Listening thread panicked.");
+	assert!(result.is_err());
+}
+
+#[test]
+fn test_http_client_send_roundtrip() {
+	use http::{Response, StatusCode};
+
+	let listener = ::std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+	let addr = listener.local_addr().unwrap();
+
+	let listening_thread = ::std::thread::spawn(move || {
+		let stream = listener.accept().unwrap().0;
+		let mut http_request = HttpRequest::from_tcp_stream(stream).unwrap();
+
+		let mut response = Response::builder();
+		response.status(StatusCode::OK);
+		let response = response.body(b"pong".to_vec()).unwrap();
+		http_request.respond(response).unwrap();
+	});
+
+	let stream = ::std::net::TcpStream::connect(addr).unwrap();
+
+	let mut request = http::Request::builder();
+	request.method(http::Method::GET);
+	request.uri("/ping");
+	let request = request.body(Vec::new()).unwrap();
+
+	let response = HttpClient::send(stream, request).expect("Failed to send request.");
 
-	assert_eq!(
-		response.body(),
-		&"hello me"
-			.as_bytes()
-			.iter()
-			.map(|x| *x)
-			.collect::<Vec<u8>>()
-	);
+	assert_eq!(response.status(), StatusCode::OK);
+	assert_eq!(response.body(), &b"pong".to_vec());
 
-	//listening_thread.join().expect("Thread joining failed.");
+	listening_thread.join().expect("Listening thread panicked.");
 }